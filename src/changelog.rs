@@ -0,0 +1,270 @@
+// Copyright 2024 David Smith <david@narigama.dev>
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not
+// use this file except in compliance with the License. You may obtain a copy
+// of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS, WITHOUT
+// WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the
+// License for the specific language governing permissions and limitations
+// under the License.
+
+use crate::{model::Version, run_command};
+
+/// the level a conventional commit implies, ordered so the highest found wins
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BumpLevel {
+    Patch,
+    Minor,
+    Major,
+}
+
+/// a single commit parsed as `type(scope)?!: description`
+#[derive(Debug)]
+pub struct ConventionalCommit {
+    pub kind: String,
+    pub scope: Option<String>,
+    pub breaking: bool,
+    pub description: String,
+}
+
+// commits/fields are separated with control characters that can't appear in
+// a commit subject or body, so splitting on them is unambiguous
+const COMMIT_SEPARATOR: &str = "\u{1}";
+const FIELD_SEPARATOR: &str = "\u{2}";
+
+/// the most recent `v*` tag reachable from HEAD, or `None` if there isn't one yet
+pub fn last_tag() -> eyre::Result<Option<String>> {
+    let output = run_command(std::process::Command::new("git").args([
+        "tag",
+        "--merged",
+        "HEAD",
+        "--list",
+        "v*",
+        "--sort=-creatordate",
+    ]))?;
+
+    Ok(String::from_utf8(output.stdout)?
+        .lines()
+        .next()
+        .map(str::to_string))
+}
+
+/// the subject and body of every commit since `tag` (or the whole history if `tag` is `None`)
+fn commits_since(tag: Option<&str>) -> eyre::Result<Vec<(String, String)>> {
+    let range = match tag {
+        Some(tag) => format!("{tag}..HEAD"),
+        None => "HEAD".to_string(),
+    };
+
+    let output = run_command(std::process::Command::new("git").args([
+        "log",
+        &range,
+        &format!("--pretty=format:%s{FIELD_SEPARATOR}%b{COMMIT_SEPARATOR}"),
+    ]))?;
+
+    Ok(String::from_utf8(output.stdout)?
+        .split(COMMIT_SEPARATOR)
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| entry.split_once(FIELD_SEPARATOR))
+        .map(|(subject, body)| (subject.to_string(), body.to_string()))
+        .collect())
+}
+
+/// parse a commit subject as `type(scope)?!: description`, returning `None` if it isn't one
+fn parse_subject(subject: &str) -> Option<ConventionalCommit> {
+    let (head, description) = subject.split_once(':')?;
+    let description = description.trim().to_string();
+
+    let (head, breaking) = match head.strip_suffix('!') {
+        Some(head) => (head, true),
+        None => (head, false),
+    };
+
+    let (kind, scope) = match head.split_once('(') {
+        Some((kind, rest)) => (kind, rest.strip_suffix(')').map(str::to_string)),
+        None => (head, None),
+    };
+
+    if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+
+    Some(ConventionalCommit {
+        kind: kind.to_string(),
+        scope,
+        breaking,
+        description,
+    })
+}
+
+/// every conventional commit since the last `v*` tag
+pub fn conventional_commits_since_last_tag() -> eyre::Result<Vec<ConventionalCommit>> {
+    let tag = last_tag()?;
+
+    Ok(commits_since(tag.as_deref())?
+        .into_iter()
+        .filter_map(|(subject, body)| {
+            let mut commit = parse_subject(&subject)?;
+            commit.breaking = commit.breaking || body.contains("BREAKING CHANGE:");
+            Some(commit)
+        })
+        .collect())
+}
+
+/// the highest bump level implied by `commits`; for pre-1.0 versions
+/// (`major == 0`) breaking changes only imply a minor bump and features only
+/// imply a patch bump, matching standard pre-release semver convention
+pub fn bump_level(commits: &[ConventionalCommit], major: u64) -> Option<BumpLevel> {
+    commits
+        .iter()
+        .filter_map(|commit| {
+            let level = if commit.breaking {
+                BumpLevel::Major
+            } else if commit.kind == "feat" {
+                BumpLevel::Minor
+            } else if commit.kind == "fix" || commit.kind == "perf" {
+                BumpLevel::Patch
+            } else {
+                return None;
+            };
+
+            Some(if major == 0 {
+                match level {
+                    BumpLevel::Major => BumpLevel::Minor,
+                    BumpLevel::Minor | BumpLevel::Patch => BumpLevel::Patch,
+                }
+            } else {
+                level
+            })
+        })
+        .max()
+}
+
+/// the CHANGELOG.md section heading a commit `kind` belongs under
+fn section_title(kind: &str) -> &'static str {
+    match kind {
+        "feat" => "Features",
+        "fix" => "Bug Fixes",
+        "perf" => "Performance Improvements",
+        _ => "Other Changes",
+    }
+}
+
+/// render a `## [<version>] - <date>` section, grouping `commits` by type
+pub fn render_section(version: &Version, date: &str, commits: &[ConventionalCommit]) -> String {
+    let mut sections: Vec<(&'static str, Vec<&ConventionalCommit>)> = Vec::new();
+    for commit in commits {
+        let title = section_title(&commit.kind);
+        match sections.iter_mut().find(|(existing, _)| *existing == title) {
+            Some((_, entries)) => entries.push(commit),
+            None => sections.push((title, vec![commit])),
+        }
+    }
+
+    let mut section = format!("## [{version}] - {date}\n\n");
+    for (title, entries) in sections {
+        section.push_str(&format!("### {title}\n\n"));
+        for commit in entries {
+            match &commit.scope {
+                Some(scope) => {
+                    section.push_str(&format!("- **{scope}:** {}\n", commit.description))
+                }
+                None => section.push_str(&format!("- {}\n", commit.description)),
+            }
+        }
+        section.push('\n');
+    }
+
+    section
+}
+
+/// prepend `section` to `path`, creating the file if it doesn't exist yet
+pub fn prepend(path: &std::path::Path, section: &str) -> eyre::Result<()> {
+    let existing = std::fs::read_to_string(path).unwrap_or_default();
+    std::fs::write(path, format!("{section}{existing}"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(kind: &str, breaking: bool) -> ConventionalCommit {
+        ConventionalCommit {
+            kind: kind.to_string(),
+            scope: None,
+            breaking,
+            description: String::new(),
+        }
+    }
+
+    #[test]
+    fn parses_plain_subject() {
+        let commit = parse_subject("feat: add workspace support").unwrap();
+        assert_eq!(commit.kind, "feat");
+        assert_eq!(commit.scope, None);
+        assert!(!commit.breaking);
+        assert_eq!(commit.description, "add workspace support");
+    }
+
+    #[test]
+    fn parses_scope() {
+        let commit = parse_subject("fix(cli): handle empty args").unwrap();
+        assert_eq!(commit.kind, "fix");
+        assert_eq!(commit.scope.as_deref(), Some("cli"));
+    }
+
+    #[test]
+    fn parses_breaking_bang() {
+        let commit = parse_subject("feat!: drop legacy config").unwrap();
+        assert!(commit.breaking);
+    }
+
+    #[test]
+    fn parses_breaking_bang_with_scope() {
+        let commit = parse_subject("feat(api)!: drop legacy config").unwrap();
+        assert_eq!(commit.scope.as_deref(), Some("api"));
+        assert!(commit.breaking);
+    }
+
+    #[test]
+    fn rejects_non_conventional_subjects() {
+        assert!(parse_subject("fixed a typo").is_none());
+        assert!(parse_subject("Merge branch 'main'").is_none());
+    }
+
+    #[test]
+    fn bump_level_picks_the_highest_found() {
+        let commits = vec![commit("fix", false), commit("feat", false)];
+        assert_eq!(bump_level(&commits, 1), Some(BumpLevel::Minor));
+    }
+
+    #[test]
+    fn bump_level_breaking_wins_over_feat_and_fix() {
+        let commits = vec![commit("fix", false), commit("feat", true)];
+        assert_eq!(bump_level(&commits, 1), Some(BumpLevel::Major));
+    }
+
+    #[test]
+    fn bump_level_ignores_non_bumping_kinds() {
+        let commits = vec![commit("chore", false), commit("docs", false)];
+        assert_eq!(bump_level(&commits, 1), None);
+    }
+
+    #[test]
+    fn bump_level_downgrades_for_pre_1_0() {
+        let commits = vec![commit("feat", true)];
+        assert_eq!(bump_level(&commits, 0), Some(BumpLevel::Minor));
+
+        let commits = vec![commit("feat", false)];
+        assert_eq!(bump_level(&commits, 0), Some(BumpLevel::Patch));
+
+        let commits = vec![commit("fix", false)];
+        assert_eq!(bump_level(&commits, 0), Some(BumpLevel::Patch));
+    }
+}
@@ -17,7 +17,9 @@ use toml_edit::DocumentMut;
 
 use eyre::{Context, OptionExt};
 
+pub mod changelog;
 pub mod model;
+pub mod workspace;
 
 #[derive(Debug, Parser)]
 pub enum Command {
@@ -27,6 +29,15 @@ pub enum Command {
     Minor,
     /// bump the major version (e.g. 1.1.1 => 2.0.0)
     Major,
+    /// bump to, or advance, a pre-release (e.g. 1.2.3 => 1.2.4-rc.1 => 1.2.4-rc.2)
+    Prerelease {
+        /// the pre-release label, e.g. `rc`, `beta`
+        label: String,
+    },
+    /// strip pre-release/build metadata, promoting the current version to a final release
+    Release,
+    /// decide the bump level from conventional commits since the last tag, and update CHANGELOG.md
+    Auto,
 }
 
 #[derive(Debug, Parser)]
@@ -40,11 +51,107 @@ pub enum Args {
         #[clap(long)]
         git: bool,
 
+        /// re-tag even if the computed version's tag already exists
+        #[clap(long)]
+        force: bool,
+
+        /// GPG-sign the release commit (`-S`) and tag (`-s`)
+        #[clap(long)]
+        sign: bool,
+
+        /// push the commit and tag after a successful release
+        #[clap(long)]
+        push: bool,
+
+        /// template for the git tag, expanded with `{version}` (default: `v{version}`, or
+        /// `[package.metadata.semver] tag_format` in Cargo.toml)
+        #[clap(long)]
+        tag_format: Option<String>,
+
+        /// template for the commit message, expanded with `{version}` (same defaulting as
+        /// `--tag-format`)
+        #[clap(long)]
+        message_format: Option<String>,
+
         #[clap(subcommand)]
         command: Command,
     },
 }
 
+/// the flags that govern how `commit_with_tag` behaves
+#[derive(Debug)]
+pub struct GitOptions {
+    pub force: bool,
+    pub sign: bool,
+    pub push: bool,
+    pub tag_format: String,
+    pub message_format: String,
+}
+
+/// `[package.metadata.semver]` (or `[workspace.metadata.semver]`)
+/// `tag_format`/`message_format` defaults
+fn read_template_defaults(
+    path: &std::path::Path,
+) -> eyre::Result<(Option<String>, Option<String>)> {
+    let cargo = std::fs::read_to_string(path)
+        .context(format!("Couldn't find {path:?}"))?
+        .parse::<DocumentMut>()
+        .context(format!("Unable to parse {path:?}"))?;
+
+    let metadata = cargo
+        .get("package")
+        .and_then(|package| package.get("metadata"))
+        .and_then(|metadata| metadata.get("semver"))
+        .or_else(|| {
+            cargo
+                .get("workspace")
+                .and_then(|workspace| workspace.get("metadata"))
+                .and_then(|metadata| metadata.get("semver"))
+        });
+
+    let tag_format = metadata
+        .and_then(|metadata| metadata.get("tag_format"))
+        .and_then(|item| item.as_str())
+        .map(str::to_string);
+
+    let message_format = metadata
+        .and_then(|metadata| metadata.get("message_format"))
+        .and_then(|item| item.as_str())
+        .map(str::to_string);
+
+    Ok((tag_format, message_format))
+}
+
+/// resolve the effective tag/message templates: the CLI flag wins, then
+/// `[package.metadata.semver]` in Cargo.toml, then the `v{version}` default
+fn resolve_git_options(
+    path: &std::path::Path,
+    tag_format: Option<String>,
+    message_format: Option<String>,
+    force: bool,
+    sign: bool,
+    push: bool,
+) -> eyre::Result<GitOptions> {
+    let (default_tag_format, default_message_format) = read_template_defaults(path)?;
+
+    Ok(GitOptions {
+        force,
+        sign,
+        push,
+        tag_format: tag_format
+            .or(default_tag_format)
+            .unwrap_or_else(|| "v{version}".to_string()),
+        message_format: message_format
+            .or(default_message_format)
+            .unwrap_or_else(|| "v{version}".to_string()),
+    })
+}
+
+/// expand a tag/message template's `{version}` placeholder
+fn expand_template(template: &str, version: &model::Version) -> String {
+    template.replace("{version}", &version.to_string())
+}
+
 /// parse Cargo.toml and get it's `package.version`
 fn get_cargo_version(path: &std::path::Path) -> eyre::Result<model::Version> {
     let cargo = std::fs::read_to_string(path)
@@ -85,40 +192,154 @@ fn set_cargo_version(path: &std::path::Path, version_new: &model::Version) -> ey
     // run `cargo check` to make sure the .lock is also up to date
     let mut command = std::process::Command::new("cargo");
     command.arg("check");
-    command.output()?;
+    run_command(&mut command)?;
 
     Ok(())
 }
 
+/// run `command`, surfacing a non-zero exit status as an error instead of silently continuing
+fn run_command(command: &mut std::process::Command) -> eyre::Result<std::process::Output> {
+    let output = command.output()?;
+    eyre::ensure!(
+        output.status.success(),
+        "`{command:?}` failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    Ok(output)
+}
+
 /// check there aren't pending changes, make sure the working dir is clean before making changes
 fn is_working_dir_clean() -> eyre::Result<bool> {
     let mut command = std::process::Command::new("git");
     command.args("status --porcelain".split_ascii_whitespace());
 
-    let output = command.output()?;
+    let output = run_command(&mut command)?;
     Ok(String::from_utf8(output.stdout)?.trim().is_empty())
 }
 
-/// commit Cargo.toml and Cargo.lock and tag the version
-fn commit_with_tag(version: &model::Version) -> eyre::Result<()> {
+/// true if `refs/tags/{tag}` already exists
+fn tag_exists(tag: &str) -> eyre::Result<bool> {
+    let status = std::process::Command::new("git")
+        .args(["rev-parse", "-q", "--verify", &format!("refs/tags/{tag}")])
+        .status()
+        .context("unable to run `git rev-parse`")?;
+    Ok(status.success())
+}
+
+/// commit the given paths (manifests plus `Cargo.lock`) and tag the version
+fn commit_with_tag(
+    paths: &[std::path::PathBuf],
+    version: &model::Version,
+    options: &GitOptions,
+) -> eyre::Result<()> {
+    let tag = expand_template(&options.tag_format, version);
+    let message = expand_template(&options.message_format, version);
+
+    if !options.force && tag_exists(&tag)? {
+        eyre::bail!("tag `{tag}` already exists; pass --force to re-create it");
+    }
+
     // add files
     let mut command = std::process::Command::new("git");
-    command.args(
-        "add Cargo.toml Cargo.lock"
-            .to_string()
-            .split_ascii_whitespace(),
-    );
-    command.output()?;
+    command.arg("add").args(paths);
+    run_command(&mut command)?;
 
     // commit
     let mut command = std::process::Command::new("git");
-    command.args(format!("commit -m v{version}").split_ascii_whitespace());
-    command.output()?;
+    command.args(["commit", "-m", &message]);
+    if options.sign {
+        command.arg("-S");
+    }
+    run_command(&mut command)?;
 
     // add tag
     let mut command = std::process::Command::new("git");
-    command.args(format!("tag v{version}").split_ascii_whitespace());
-    command.output()?;
+    command.args(["tag", &tag]);
+    if options.sign {
+        command.arg("-s");
+    }
+    if options.force {
+        command.arg("-f");
+    }
+    run_command(&mut command)?;
+
+    if options.push {
+        run_command(std::process::Command::new("git").args(["push"]))?;
+        run_command(std::process::Command::new("git").args(["push", "--tags"]))?;
+    }
+
+    Ok(())
+}
+
+/// apply a bump `Command` to `version`, producing the new version
+fn apply_command(version: &model::Version, command: &Command) -> eyre::Result<model::Version> {
+    Ok(match command {
+        Command::Patch => version.patch_version(),
+        Command::Minor => version.minor_version(),
+        Command::Major => version.major_version(),
+        Command::Prerelease { label } => version.prerelease_version(label)?,
+        Command::Release => version.release_version(),
+        Command::Auto => eyre::bail!("`auto` must be resolved before calling `apply_command`"),
+    })
+}
+
+/// today's date as `YYYY-MM-DD`, for the CHANGELOG.md heading
+fn current_date() -> eyre::Result<String> {
+    let output = run_command(std::process::Command::new("date").arg("+%Y-%m-%d"))?;
+    Ok(String::from_utf8(output.stdout)?.trim().to_string())
+}
+
+/// decide the bump level from conventional commits since the last tag, bump
+/// the version, and prepend a CHANGELOG.md section
+fn auto_bump(
+    path: &std::path::Path,
+    version: &model::Version,
+    dry_run: bool,
+    git: bool,
+    git_options: &GitOptions,
+) -> eyre::Result<()> {
+    let commits = changelog::conventional_commits_since_last_tag()?;
+    let Some(level) = changelog::bump_level(&commits, version.major) else {
+        println!("No conventional commits implying a bump found since the last tag. Nothing to do.");
+        return Ok(());
+    };
+
+    let version_new = match level {
+        changelog::BumpLevel::Patch => version.patch_version(),
+        changelog::BumpLevel::Minor => version.minor_version(),
+        changelog::BumpLevel::Major => version.major_version(),
+    };
+
+    println!("Promoting from {version} to {version_new}");
+    if git {
+        println!("--git was included, will commit and tag this version")
+    }
+
+    if dry_run {
+        println!("But this was a --dry-run. Not actually doing anything...");
+        return Ok(());
+    }
+
+    set_cargo_version(path, &version_new)?;
+    println!("Wrote changes to {path:?}");
+
+    let changelog_path = std::path::PathBuf::from("CHANGELOG.md");
+    let date = current_date()?;
+    let section = changelog::render_section(&version_new, &date, &commits);
+    changelog::prepend(&changelog_path, &section)?;
+    println!("Wrote changes to {changelog_path:?}");
+
+    if git {
+        commit_with_tag(
+            &[
+                path.to_path_buf(),
+                std::path::PathBuf::from("Cargo.lock"),
+                changelog_path,
+            ],
+            &version_new,
+            git_options,
+        )?;
+    }
 
     Ok(())
 }
@@ -127,14 +348,21 @@ fn main() -> eyre::Result<()> {
     let path = std::path::PathBuf::new().join("Cargo.toml");
 
     let args = Args::parse();
-    let version = get_cargo_version(&path)?;
 
     match args {
         Args::Semver {
             dry_run,
             git,
+            force,
+            sign,
+            push,
+            tag_format,
+            message_format,
             command,
         } => {
+            let git_options =
+                resolve_git_options(&path, tag_format, message_format, force, sign, push)?;
+
             if !dry_run && !is_working_dir_clean()? {
                 println!(
                     "Working directory doesn't appear to be clean. Commit your changes first."
@@ -142,11 +370,22 @@ fn main() -> eyre::Result<()> {
                 return Ok(());
             }
 
-            let version_new = match &command {
-                Command::Patch => version.patch_version(),
-                Command::Minor => version.minor_version(),
-                Command::Major => version.major_version(),
-            };
+            let root = std::fs::read_to_string(&path)
+                .context(format!("Couldn't find {path:?}"))?
+                .parse::<DocumentMut>()
+                .context(format!("Unable to parse {path:?}"))?;
+
+            if workspace::is_workspace(&root) {
+                return workspace::bump(&path, &command, dry_run, git, &git_options);
+            }
+
+            let version = get_cargo_version(&path)?;
+
+            if let Command::Auto = command {
+                return auto_bump(&path, &version, dry_run, git, &git_options);
+            }
+
+            let version_new = apply_command(&version, &command)?;
 
             println!("Promoting from {version} to {version_new}");
             if git {
@@ -160,7 +399,11 @@ fn main() -> eyre::Result<()> {
                     println!("Wrote changes to {:?}", &path);
 
                     if git {
-                        commit_with_tag(&version_new)?;
+                        commit_with_tag(
+                            &[path.clone(), std::path::PathBuf::from("Cargo.lock")],
+                            &version_new,
+                            &git_options,
+                        )?;
                     }
                 }
             }
@@ -14,19 +14,68 @@
 
 use std::{fmt::Display, str::FromStr};
 
-use eyre::{Context, OptionExt};
+use eyre::{Context, OptionExt, bail};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Version {
     pub major: u64,
     pub minor: u64,
     pub patch: u64,
+    pub pre: Vec<String>,
+    pub build: Vec<String>,
+}
+
+/// split dot-separated identifiers and make sure none of them are empty;
+/// when `reject_leading_zero` is set, numeric identifiers with a leading
+/// zero are rejected too (per the SemVer grammar, this only applies to
+/// pre-release identifiers, not build metadata)
+fn parse_identifiers(value: &str, reject_leading_zero: bool) -> eyre::Result<Vec<String>> {
+    value
+        .split('.')
+        .map(|identifier| {
+            if identifier.is_empty() {
+                bail!("identifiers must not be empty");
+            }
+
+            if reject_leading_zero
+                && identifier.len() > 1
+                && identifier.starts_with('0')
+                && identifier.chars().all(|c| c.is_ascii_digit())
+            {
+                bail!("numeric identifier {identifier:?} must not have a leading zero");
+            }
+
+            Ok(identifier.to_string())
+        })
+        .collect()
+}
+
+/// pre-release identifiers: numeric ones must not have a leading zero
+fn parse_pre_release_identifiers(value: &str) -> eyre::Result<Vec<String>> {
+    parse_identifiers(value, true)
+}
+
+/// build metadata identifiers: unlike pre-release, leading zeros are allowed
+fn parse_build_identifiers(value: &str) -> eyre::Result<Vec<String>> {
+    parse_identifiers(value, false)
 }
 
 impl FromStr for Version {
     type Err = eyre::Error;
 
     fn from_str(value: &str) -> eyre::Result<Self> {
+        // build metadata is split off first, then pre-release, leaving the
+        // `major.minor.patch` core behind
+        let (value, build) = match value.split_once('+') {
+            Some((value, build)) => (value, parse_build_identifiers(build)?),
+            None => (value, Vec::new()),
+        };
+
+        let (value, pre) = match value.split_once('-') {
+            Some((value, pre)) => (value, parse_pre_release_identifiers(pre)?),
+            None => (value, Vec::new()),
+        };
+
         let mut parts = value.split('.');
 
         let major = parts
@@ -51,13 +100,25 @@ impl FromStr for Version {
             major,
             minor,
             patch,
+            pre,
+            build,
         })
     }
 }
 
 impl Display for Version {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+
+        if !self.pre.is_empty() {
+            write!(f, "-{}", self.pre.join("."))?;
+        }
+
+        if !self.build.is_empty() {
+            write!(f, "+{}", self.build.join("."))?;
+        }
+
+        Ok(())
     }
 }
 
@@ -67,6 +128,8 @@ impl Version {
             major: self.major,
             minor: self.minor,
             patch: self.patch + 1,
+            pre: Vec::new(),
+            build: Vec::new(),
         }
     }
 
@@ -75,6 +138,8 @@ impl Version {
             major: self.major,
             minor: self.minor + 1,
             patch: 0,
+            pre: Vec::new(),
+            build: Vec::new(),
         }
     }
 
@@ -83,6 +148,153 @@ impl Version {
             major: self.major + 1,
             minor: 0,
             patch: 0,
+            pre: Vec::new(),
+            build: Vec::new(),
+        }
+    }
+
+    /// promote this version to a new pre-release under `label`: if this is
+    /// currently a stable version, bump the patch and start at `.1`; if it's
+    /// already a pre-release with a matching label, increment the trailing
+    /// numeric identifier; if it's already a pre-release under a different
+    /// (or irregularly-shaped) label, switch labels at the *current* patch
+    /// instead of bumping it again, since the version hasn't released yet
+    pub fn prerelease_version(&self, label: &str) -> eyre::Result<Self> {
+        if let [existing_label, counter] = self.pre.as_slice()
+            && existing_label == label
+        {
+            let counter: u64 = counter
+                .parse()
+                .context("unable to parse pre-release counter into u64")?;
+
+            return Ok(Self {
+                major: self.major,
+                minor: self.minor,
+                patch: self.patch,
+                pre: vec![label.to_string(), (counter + 1).to_string()],
+                build: Vec::new(),
+            });
+        }
+
+        if !self.pre.is_empty() {
+            return Ok(Self {
+                major: self.major,
+                minor: self.minor,
+                patch: self.patch,
+                pre: vec![label.to_string(), "1".to_string()],
+                build: Vec::new(),
+            });
         }
+
+        let next = self.patch_version();
+        Ok(Self {
+            pre: vec![label.to_string(), "1".to_string()],
+            ..next
+        })
+    }
+
+    /// strip any pre-release/build metadata, promoting this version to a
+    /// final release
+    pub fn release_version(&self) -> Self {
+        Self {
+            major: self.major,
+            minor: self.minor,
+            patch: self.patch,
+            pre: Vec::new(),
+            build: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_major_minor_patch() {
+        let version: Version = "1.2.3".parse().unwrap();
+        assert_eq!((version.major, version.minor, version.patch), (1, 2, 3));
+        assert!(version.pre.is_empty());
+        assert!(version.build.is_empty());
+    }
+
+    #[test]
+    fn round_trips_pre_release_and_build_metadata() {
+        let version: Version = "1.2.0-rc.1+build.5".parse().unwrap();
+        assert_eq!(version.pre, vec!["rc".to_string(), "1".to_string()]);
+        assert_eq!(version.build, vec!["build".to_string(), "5".to_string()]);
+        assert_eq!(version.to_string(), "1.2.0-rc.1+build.5");
+    }
+
+    #[test]
+    fn rejects_leading_zero_numeric_identifiers() {
+        assert!("1.2.0-01".parse::<Version>().is_err());
+    }
+
+    #[test]
+    fn allows_leading_zero_in_non_numeric_identifiers() {
+        assert!("1.2.0-0a".parse::<Version>().is_ok());
+    }
+
+    #[test]
+    fn allows_leading_zero_in_build_metadata() {
+        assert!("1.2.3+001".parse::<Version>().is_ok());
+        assert!("1.2.3+0.01.5".parse::<Version>().is_ok());
+    }
+
+    #[test]
+    fn rejects_empty_identifiers() {
+        assert!("1.2.0-".parse::<Version>().is_err());
+        assert!("1.2.0-rc..1".parse::<Version>().is_err());
+    }
+
+    #[test]
+    fn patch_minor_major_clear_pre_and_build() {
+        let version: Version = "1.2.3-rc.1+build.5".parse().unwrap();
+        assert_eq!(version.patch_version().to_string(), "1.2.4");
+        assert_eq!(version.minor_version().to_string(), "1.3.0");
+        assert_eq!(version.major_version().to_string(), "2.0.0");
+    }
+
+    #[test]
+    fn prerelease_from_stable_bumps_patch() {
+        let version: Version = "1.2.3".parse().unwrap();
+        assert_eq!(
+            version.prerelease_version("rc").unwrap().to_string(),
+            "1.2.4-rc.1"
+        );
+    }
+
+    #[test]
+    fn prerelease_with_matching_label_increments_counter() {
+        let version: Version = "1.2.4-rc.1".parse().unwrap();
+        assert_eq!(
+            version.prerelease_version("rc").unwrap().to_string(),
+            "1.2.4-rc.2"
+        );
+    }
+
+    #[test]
+    fn prerelease_with_different_label_does_not_bump_patch_again() {
+        let version: Version = "1.2.3-beta.1".parse().unwrap();
+        assert_eq!(
+            version.prerelease_version("rc").unwrap().to_string(),
+            "1.2.3-rc.1"
+        );
+    }
+
+    #[test]
+    fn prerelease_with_irregular_shape_does_not_bump_patch_again() {
+        let version: Version = "1.2.3-SNAPSHOT".parse().unwrap();
+        assert_eq!(
+            version.prerelease_version("rc").unwrap().to_string(),
+            "1.2.3-rc.1"
+        );
+    }
+
+    #[test]
+    fn release_strips_pre_and_build() {
+        let version: Version = "1.2.3-rc.2+build.5".parse().unwrap();
+        assert_eq!(version.release_version().to_string(), "1.2.3");
     }
 }
@@ -0,0 +1,444 @@
+// Copyright 2024 David Smith <david@narigama.dev>
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not
+// use this file except in compliance with the License. You may obtain a copy
+// of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS, WITHOUT
+// WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied. See the
+// License for the specific language governing permissions and limitations
+// under the License.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use eyre::{Context, OptionExt};
+use toml_edit::{DocumentMut, Item, Value};
+
+use crate::{Command, apply_command, model::Version};
+
+/// the tables that can hold a requirement on another workspace member
+const DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// a single crate within the workspace: its manifest path, name, and parsed document
+pub struct Member {
+    pub path: PathBuf,
+    pub name: String,
+    pub document: DocumentMut,
+}
+
+/// true if `document` declares a `[workspace]` table
+pub fn is_workspace(document: &DocumentMut) -> bool {
+    document.get("workspace").is_some()
+}
+
+/// read the root `[workspace] members` globs and resolve them into concrete
+/// member `Cargo.toml` paths, relative to `root_dir`
+pub fn find_member_paths(root_dir: &Path, document: &DocumentMut) -> eyre::Result<Vec<PathBuf>> {
+    let Some(members) = document
+        .get("workspace")
+        .and_then(|workspace| workspace.get("members"))
+        .and_then(|members| members.as_array())
+    else {
+        return Ok(Vec::new());
+    };
+
+    let mut paths = Vec::new();
+    for glob in members.iter().filter_map(|value| value.as_str()) {
+        paths.extend(resolve_glob(root_dir, glob)?);
+    }
+
+    Ok(paths)
+}
+
+/// resolve a single member glob (e.g. `crates/*` or `cli`) into the manifests
+/// it refers to; only a single trailing `/*` wildcard is supported, which
+/// covers the overwhelming majority of real-world workspaces
+fn resolve_glob(root_dir: &Path, glob: &str) -> eyre::Result<Vec<PathBuf>> {
+    match glob.strip_suffix("/*") {
+        Some(prefix) => {
+            let dir = root_dir.join(prefix);
+            let mut paths = Vec::new();
+            for entry in std::fs::read_dir(&dir).context(format!("Unable to read {dir:?}"))? {
+                let entry = entry?;
+                let manifest = entry.path().join("Cargo.toml");
+                if entry.file_type()?.is_dir() && manifest.exists() {
+                    paths.push(manifest);
+                }
+            }
+            paths.sort();
+            Ok(paths)
+        }
+        None => Ok(vec![root_dir.join(glob).join("Cargo.toml")]),
+    }
+}
+
+/// load and parse every member manifest
+pub fn load_members(paths: &[PathBuf]) -> eyre::Result<Vec<Member>> {
+    paths
+        .iter()
+        .map(|path| {
+            let document = std::fs::read_to_string(path)
+                .context(format!("Couldn't find {path:?}"))?
+                .parse::<DocumentMut>()
+                .context(format!("Unable to parse {path:?}"))?;
+
+            let name = document
+                .get("package")
+                .ok_or_eyre(format!("Couldn't find `[package]` in {path:?}"))?
+                .get("name")
+                .ok_or_eyre(format!("Couldn't find `name` in {path:?}"))?
+                .as_str()
+                .ok_or_eyre("Unable to convert `name` into a string.")?
+                .to_string();
+
+            Ok(Member {
+                path: path.clone(),
+                name,
+                document,
+            })
+        })
+        .collect()
+}
+
+/// true if `package.version` of this member is `{ workspace = true }`,
+/// i.e. it inherits from `[workspace.package] version`
+fn inherits_version(document: &DocumentMut) -> bool {
+    document
+        .get("package")
+        .and_then(|package| package.get("version"))
+        .and_then(|version| version.as_value())
+        .and_then(Value::as_inline_table)
+        .and_then(|table| table.get("workspace"))
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+}
+
+fn get_member_version(member: &Member) -> eyre::Result<Version> {
+    member
+        .document
+        .get("package")
+        .ok_or_eyre(format!("Couldn't find `[package]` in {:?}", member.path))?
+        .get("version")
+        .ok_or_eyre(format!("Couldn't find `version` in {:?}", member.path))?
+        .as_str()
+        .ok_or_eyre("Unable to convert `version` into a string.")?
+        .parse()
+}
+
+fn set_member_version(member: &mut Member, version: &Version) -> eyre::Result<()> {
+    let item = member
+        .document
+        .get_mut("package")
+        .ok_or_eyre(format!("Couldn't find `[package]` in {:?}", member.path))?
+        .get_mut("version")
+        .ok_or_eyre(format!("Couldn't find `version` in {:?}", member.path))?;
+    *item = toml_edit::value(version.to_string());
+    Ok(())
+}
+
+fn get_shared_version(root: &DocumentMut) -> Option<Version> {
+    root.get("workspace")?
+        .get("package")?
+        .get("version")?
+        .as_str()?
+        .parse()
+        .ok()
+}
+
+fn set_shared_version(root: &mut DocumentMut, version: &Version) {
+    if let Some(item) = root
+        .get_mut("workspace")
+        .and_then(|workspace| workspace.get_mut("package"))
+        .and_then(|package| package.get_mut("version"))
+    {
+        *item = toml_edit::value(version.to_string());
+    }
+}
+
+/// the only requirement operators we know how to rewrite in place
+const KNOWN_REQUIREMENT_PREFIXES: [&str; 7] = ["^", "~", "=", ">=", "<=", ">", "<"];
+
+/// given an old requirement string, keep its operator prefix (`^`, `~`,
+/// `>=`, ...) and swap in the new version, e.g. `^1.2.3` -> `^1.3.0`;
+/// returns `None` if `old` isn't a single bare/operator-prefixed version
+/// (e.g. a comma-separated range like `">=1.2, <2.0"`), so the caller can
+/// leave it untouched rather than silently drop the rest of the requirement
+fn rewrite_requirement(old: &str, new_version: &Version) -> Option<String> {
+    let trimmed = old.trim();
+    if trimmed.contains(',') || trimmed.contains(char::is_whitespace) {
+        return None;
+    }
+
+    let prefix_len = trimmed.find(|c: char| c.is_ascii_digit())?;
+    let prefix = &trimmed[..prefix_len];
+    if !prefix.is_empty() && !KNOWN_REQUIREMENT_PREFIXES.contains(&prefix) {
+        return None;
+    }
+
+    Some(format!("{prefix}{new_version}"))
+}
+
+/// rewrite every requirement on a changed workspace member found in
+/// `dependencies`/`dev-dependencies`/`build-dependencies`, including the
+/// `version` key of detailed `{ path = ..., version = ... }` entries; a
+/// requirement `rewrite_requirement` doesn't understand is left untouched
+/// and reported so it can be fixed up by hand
+fn update_dependencies(document: &mut DocumentMut, changed: &BTreeMap<String, Version>) {
+    for table_name in DEPENDENCY_TABLES {
+        let Some(table) = document
+            .get_mut(table_name)
+            .and_then(Item::as_table_like_mut)
+        else {
+            continue;
+        };
+
+        for (name, new_version) in changed {
+            let Some(item) = table.get_mut(name) else {
+                continue;
+            };
+
+            match item {
+                Item::Value(Value::String(requirement)) => {
+                    match rewrite_requirement(requirement.value(), new_version) {
+                        Some(rewritten) => *item = toml_edit::value(rewritten),
+                        None => println!(
+                            "warning: leaving requirement on `{name}` (`{}`) untouched; it isn't a simple version requirement",
+                            requirement.value()
+                        ),
+                    }
+                }
+                Item::Value(Value::InlineTable(inline)) => {
+                    if let Some(requirement) = inline.get("version").and_then(Value::as_str) {
+                        match rewrite_requirement(requirement, new_version) {
+                            Some(rewritten) => {
+                                inline.insert("version", rewritten.into());
+                            }
+                            None => println!(
+                                "warning: leaving requirement on `{name}` (`{requirement}`) untouched; it isn't a simple version requirement"
+                            ),
+                        }
+                    }
+                }
+                Item::Table(table) => {
+                    if let Some(requirement) = table.get("version").and_then(Item::as_str) {
+                        match rewrite_requirement(requirement, new_version) {
+                            Some(rewritten) => {
+                                table.insert("version", toml_edit::value(rewritten));
+                            }
+                            None => println!(
+                                "warning: leaving requirement on `{name}` (`{requirement}`) untouched; it isn't a simple version requirement"
+                            ),
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// bump every member of the workspace rooted at `root_path` by `command`,
+/// rewriting inter-crate dependency requirements to match, then optionally
+/// write the changes and commit/tag them
+pub fn bump(
+    root_path: &Path,
+    command: &Command,
+    dry_run: bool,
+    git: bool,
+    git_options: &crate::GitOptions,
+) -> eyre::Result<()> {
+    if let Command::Auto = command {
+        eyre::bail!(
+            "`auto` is not yet supported for workspace releases; pick a bump level explicitly"
+        );
+    }
+
+    let root_dir = root_path.parent().unwrap_or_else(|| Path::new("."));
+
+    let mut root_document = std::fs::read_to_string(root_path)
+        .context(format!("Couldn't find {root_path:?}"))?
+        .parse::<DocumentMut>()
+        .context(format!("Unable to parse {root_path:?}"))?;
+
+    let member_paths = find_member_paths(root_dir, &root_document)?;
+    let mut members = load_members(&member_paths)?;
+    eyre::ensure!(!members.is_empty(), "no workspace members found to bump");
+
+    let shared_version_new = match get_shared_version(&root_document) {
+        Some(shared_version) => Some(apply_command(&shared_version, command)?),
+        None => None,
+    };
+
+    let mut changed = BTreeMap::new();
+    for member in &mut members {
+        let new_version = if inherits_version(&member.document) {
+            shared_version_new.clone().ok_or_eyre(format!(
+                "{:?} inherits `workspace.package.version`, but the workspace doesn't define one",
+                member.path
+            ))?
+        } else {
+            let current_version = get_member_version(member)?;
+            let new_version = apply_command(&current_version, command)?;
+            set_member_version(member, &new_version)?;
+            new_version
+        };
+
+        changed.insert(member.name.clone(), new_version);
+    }
+
+    println!("Bumping {} workspace member(s):", members.len());
+    for member in &members {
+        println!("  {} -> {}", member.name, changed[&member.name]);
+    }
+
+    if dry_run {
+        println!("But this was a --dry-run. Not actually doing anything...");
+        return Ok(());
+    }
+
+    for member in &mut members {
+        update_dependencies(&mut member.document, &changed);
+        std::fs::write(&member.path, member.document.to_string())?;
+    }
+
+    let mut paths = member_paths;
+    if let Some(shared_version) = &shared_version_new {
+        set_shared_version(&mut root_document, shared_version);
+        std::fs::write(root_path, root_document.to_string())?;
+        paths.push(root_path.to_path_buf());
+    }
+
+    // run `cargo check` across the workspace to keep Cargo.lock up to date
+    crate::run_command(
+        std::process::Command::new("cargo")
+            .arg("check")
+            .arg("--workspace"),
+    )?;
+
+    if git {
+        paths.push(PathBuf::from("Cargo.lock"));
+
+        // lockstep workspaces (the common case this tool targets) share a
+        // single version across all members; fall back to the first
+        // member's version if they've diverged
+        let tag_version = shared_version_new
+            .as_ref()
+            .unwrap_or_else(|| &changed[&members[0].name]);
+
+        crate::commit_with_tag(&paths, tag_version, git_options)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(value: &str) -> Version {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn rewrite_requirement_keeps_caret_prefix() {
+        assert_eq!(
+            rewrite_requirement("^1.2.3", &version("1.3.0")),
+            Some("^1.3.0".to_string())
+        );
+    }
+
+    #[test]
+    fn rewrite_requirement_keeps_bare_version() {
+        assert_eq!(
+            rewrite_requirement("1.2.3", &version("1.3.0")),
+            Some("1.3.0".to_string())
+        );
+    }
+
+    #[test]
+    fn rewrite_requirement_rejects_comma_ranges() {
+        assert_eq!(rewrite_requirement(">=1.2, <2.0", &version("1.3.0")), None);
+    }
+
+    #[test]
+    fn rewrite_requirement_rejects_unknown_prefixes() {
+        assert_eq!(rewrite_requirement("foo1.2.3", &version("1.3.0")), None);
+    }
+
+    #[test]
+    fn inherits_version_detects_workspace_true() {
+        let document: DocumentMut = "[package]\nname = \"foo\"\nversion = { workspace = true }\n"
+            .parse()
+            .unwrap();
+        assert!(inherits_version(&document));
+    }
+
+    #[test]
+    fn inherits_version_false_for_plain_version() {
+        let document: DocumentMut = "[package]\nname = \"foo\"\nversion = \"1.2.3\"\n"
+            .parse()
+            .unwrap();
+        assert!(!inherits_version(&document));
+    }
+
+    #[test]
+    fn update_dependencies_rewrites_string_requirement() {
+        let mut document: DocumentMut = "[dependencies]\nfoo = \"1.2.3\"\n".parse().unwrap();
+        let changed = BTreeMap::from([("foo".to_string(), version("1.3.0"))]);
+
+        update_dependencies(&mut document, &changed);
+
+        assert_eq!(document["dependencies"]["foo"].as_str(), Some("1.3.0"));
+    }
+
+    #[test]
+    fn update_dependencies_rewrites_inline_table_version() {
+        let mut document: DocumentMut =
+            "[dependencies]\nfoo = { path = \"../foo\", version = \"1.2.3\" }\n"
+                .parse()
+                .unwrap();
+        let changed = BTreeMap::from([("foo".to_string(), version("1.3.0"))]);
+
+        update_dependencies(&mut document, &changed);
+
+        assert_eq!(
+            document["dependencies"]["foo"]["version"].as_str(),
+            Some("1.3.0")
+        );
+    }
+
+    #[test]
+    fn update_dependencies_rewrites_table_version() {
+        let mut document: DocumentMut =
+            "[dependencies.foo]\npath = \"../foo\"\nversion = \"1.2.3\"\n"
+                .parse()
+                .unwrap();
+        let changed = BTreeMap::from([("foo".to_string(), version("1.3.0"))]);
+
+        update_dependencies(&mut document, &changed);
+
+        assert_eq!(
+            document["dependencies"]["foo"]["version"].as_str(),
+            Some("1.3.0")
+        );
+    }
+
+    #[test]
+    fn update_dependencies_leaves_comma_ranges_untouched() {
+        let mut document: DocumentMut = "[dependencies]\nfoo = \">=1.2, <2.0\"\n".parse().unwrap();
+        let changed = BTreeMap::from([("foo".to_string(), version("1.3.0"))]);
+
+        update_dependencies(&mut document, &changed);
+
+        assert_eq!(
+            document["dependencies"]["foo"].as_str(),
+            Some(">=1.2, <2.0")
+        );
+    }
+}